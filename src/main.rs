@@ -1,48 +1,330 @@
 use clap::Parser;
 use futures::{SinkExt, StreamExt};
 use pump_interface::accounts::PoolAccount;
-use spl_token::state::Account as SplAccount;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use spl_token::state::{Account as SplAccount, Mint};
+use std::collections::HashMap;
 use std::io::{self, Write};
+use std::time::Duration;
+use tokio::sync::mpsc;
 use tonic::transport::channel::ClientTlsConfig;
 use yellowstone_grpc_client::GeyserGrpcClient;
 use yellowstone_grpc_proto::prelude::{
     CommitmentLevel,
+    SlotStatus,
     SubscribeRequest,
+    SubscribeUpdateAccount,
+    SubscribeUpdateSlot,
     subscribe_update::UpdateOneof,
+    subscribe_request_filter_accounts_filter::Filter as AccountsFilterOneof,
+    subscribe_request_filter_accounts_filter_memcmp::Data as MemcmpDataOneof,
     SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterAccountsFilter,
+    SubscribeRequestFilterAccountsFilterMemcmp,
+    SubscribeRequestFilterSlots,
     SubscribeRequestPing,
 };
 
-#[derive(Debug)]
+mod sink;
+use sink::{MemorySink, PostgresSink, Sink, VaultSide};
+
+#[derive(Debug, Clone)]
 struct VaultUpdate {
     slot: u64,
     amount: f64,
+    /// Set once the slot subscription reports this slot with confirmed or
+    /// finalized status, so consumers can tell a
+    /// processed-but-rolled-back balance from a confirmed one.
+    confirmed: bool,
 }
 
+/// A derived pool price/liquidity snapshot, computed by joining the most
+/// recent base and quote vault balances.
+#[derive(Debug)]
+struct PriceTick {
+    slot: u64,
+    price: f64,
+    liquidity_quote: f64,
+    /// The Pool PDA's `lp_supply` as of the last time we saw it, carried
+    /// along for display only. It's raw LP-token base units, not
+    /// quote-denominated liquidity, and we don't know the LP mint's
+    /// decimals, so it can't be compared against `liquidity_quote` without
+    /// a matching unit conversion we don't have the inputs for.
+    lp_supply: Option<u64>,
+}
+
+/// Identifying fields and bookkeeping that aren't per-update records - the
+/// updates themselves now live in whichever `Sink` was configured.
 #[derive(Debug)]
 struct RamDb {
     pool_address: String,
     base_vault_address: String,
     quote_vault_address: String,
-    base_updates: Vec<VaultUpdate>,
-    quote_updates: Vec<VaultUpdate>,
+    /// `(first_missing_slot, last_missing_slot)` ranges inferred from gaps
+    /// in the processed-slot sequence, so a hole in the recorded updates
+    /// can be told apart from genuine inactivity.
+    slot_gaps: Vec<(u64, u64)>,
+    price_ticks: Vec<PriceTick>,
+}
+
+/// Joins the latest base/quote balances into a price tick, provided both
+/// are known, landed at the same or adjacent slots, and `base_amount` is
+/// nonzero (to avoid dividing by it).
+fn try_derive_price_tick(
+    latest_base: Option<(u64, f64)>,
+    latest_quote: Option<(u64, f64)>,
+    lp_supply: Option<u64>,
+) -> Option<PriceTick> {
+    let (base_slot, base_amount) = latest_base?;
+    let (quote_slot, quote_amount) = latest_quote?;
+    if base_amount == 0.0 || base_slot.abs_diff(quote_slot) > 1 {
+        return None;
+    }
+    let price = quote_amount / base_amount;
+    let liquidity_quote = quote_amount + base_amount * price;
+    Some(PriceTick {
+        slot: base_slot.max(quote_slot),
+        price,
+        liquidity_quote,
+        lp_supply,
+    })
+}
+
+/// Prints a derived price tick.
+fn log_price_tick(tick: &PriceTick) {
+    println!(
+        "Price @ slot {}: {:.9} quote/base, liquidity={:.6} quote (lp_supply={:?})",
+        tick.slot, tick.price, tick.liquidity_quote, tick.lp_supply
+    );
+}
+
+/// Fetches a mint account over RPC and returns its `decimals`. Mint accounts
+/// are effectively static once created, so a geyser subscription for them
+/// may never see a write - decimals are fetched once up front instead of
+/// waiting on the stream to tell us.
+async fn fetch_mint_decimals(rpc: &RpcClient, mint: &str) -> anyhow::Result<u8> {
+    let pubkey: Pubkey = mint.parse().map_err(|e| anyhow::anyhow!("invalid mint address {mint}: {e}"))?;
+    let account = rpc.get_account(&pubkey).await.map_err(|e| anyhow::anyhow!("fetching mint {mint}: {e}"))?;
+    let mint_state = Mint::unpack(&account.data).map_err(|e| anyhow::anyhow!("unpacking mint {mint}: {e}"))?;
+    Ok(mint_state.decimals)
+}
+
+/// Unpacks a vault's raw account data, records the resulting `VaultUpdate`
+/// through `sink`, updates `latest` for that side, and emits a price tick
+/// if `latest`/`other_latest` now line up.
+#[allow(clippy::too_many_arguments)]
+async fn process_vault_update(
+    label: &str,
+    pk: &str,
+    slot: u64,
+    raw_data: &[u8],
+    decimals: u8,
+    side: VaultSide,
+    pubkey: &str,
+    sink: &mut dyn Sink,
+    latest: &mut Option<(u64, f64)>,
+    other_latest: Option<(u64, f64)>,
+    latest_lp_supply: Option<u64>,
+    ram_db: &mut RamDb,
+) -> anyhow::Result<()> {
+    match SplAccount::unpack(raw_data) {
+        Ok(token_acc) => {
+            let raw = token_acc.amount;
+            let human = (raw as f64) / 10f64.powi(decimals as i32);
+            println!("{label} vault {pk} @ slot {slot}: {human:.6} tokens (raw={raw})");
+
+            let update = VaultUpdate { slot, amount: human, confirmed: false };
+            sink.record_update(&update, side, pubkey).await?;
+
+            *latest = Some((slot, human));
+            let (base_latest, quote_latest) = match side {
+                VaultSide::Base => (*latest, other_latest),
+                VaultSide::Quote => (other_latest, *latest),
+            };
+            if let Some(tick) = try_derive_price_tick(base_latest, quote_latest, latest_lp_supply) {
+                log_price_tick(&tick);
+                ram_db.price_ticks.push(tick);
+            }
+        }
+        Err(e) => eprintln!("Failed to unpack {label} vault {pk}: {e}"),
+    }
+    Ok(())
+}
+
+/// Tracks the monotonic processed-slot sequence and reports the inclusive
+/// `(start, end)` range of any slots skipped over since the last call.
+#[derive(Default)]
+struct SlotGapTracker {
+    last_processed: Option<u64>,
+}
+
+impl SlotGapTracker {
+    fn observe(&mut self, slot: u64) -> Option<(u64, u64)> {
+        let gap = match self.last_processed {
+            Some(last) if slot > last + 1 => Some((last + 1, slot - 1)),
+            _ => None,
+        };
+        if self.last_processed.map_or(true, |last| slot > last) {
+            self.last_processed = Some(slot);
+        }
+        gap
+    }
 }
 
 #[derive(Parser)]
 struct Args {
-    /// Geyser gRPC endpoint URL
-    #[clap(short, long)]
+    /// Geyser gRPC endpoint URL. May be repeated to race several sources;
+    /// the first one to report a newer update for a pubkey wins.
+    #[clap(short, long = "endpoint", required = true)]
+    endpoints: Vec<String>,
+    /// X-Token for authentication, one per `--endpoint` in the same order.
+    #[clap(long = "x-token", required = true)]
+    x_tokens: Vec<String>,
+    /// Solana JSON-RPC endpoint used to fetch the base/quote mints'
+    /// `decimals` once at startup, since mint accounts rarely change and a
+    /// geyser subscription for them may never report a write.
+    #[clap(long = "rpc-url", required = true)]
+    rpc_url: String,
+    /// Also subscribe to accounts whose owning program is this pubkey.
+    /// Repeatable; lets the tool discover vaults dynamically instead of
+    /// requiring them to be typed at the prompt.
+    #[clap(long = "owner")]
+    owners: Vec<String>,
+    /// `<offset>,<base58-bytes>` memcmp filter, matched against account
+    /// data at `offset`. Repeatable.
+    #[clap(long = "memcmp", value_parser = parse_memcmp)]
+    memcmps: Vec<(u64, String)>,
+    /// Only match accounts whose data is exactly `n` bytes long. Repeatable.
+    #[clap(long = "datasize")]
+    datasizes: Vec<u64>,
+    /// Postgres connection string. When set, vault updates are persisted
+    /// there (batched `COPY`) instead of just being kept in memory.
+    #[clap(long = "postgres-url")]
+    postgres_url: Option<String>,
+}
+
+/// Parses a `--memcmp offset,base58-bytes` CLI value.
+fn parse_memcmp(s: &str) -> anyhow::Result<(u64, String)> {
+    let (offset, bytes) = s
+        .split_once(',')
+        .ok_or_else(|| anyhow::anyhow!("expected `<offset>,<base58-bytes>`, got `{s}`"))?;
+    let offset: u64 = offset.parse()?;
+    Ok((offset, bytes.to_string()))
+}
+
+/// One geyser source to race against the others.
+struct Source {
+    id: usize,
     endpoint: String,
-    /// X-Token for authentication
-    #[clap(long)]
     x_token: String,
 }
 
+/// Tracks the newest `(slot, write_version)` seen per pubkey across all
+/// sources so a slow/stale endpoint can never clobber a fresher update.
+#[derive(Default)]
+struct Dedup {
+    seen: HashMap<Vec<u8>, (u64, u64)>,
+}
+
+impl Dedup {
+    /// Returns true if `(slot, write_version)` is strictly newer than
+    /// whatever was last recorded for `pubkey`, and records it if so.
+    fn admit(&mut self, pubkey: &[u8], slot: u64, write_version: u64) -> bool {
+        match self.seen.get(pubkey) {
+            Some(&(last_slot, last_wv)) if (slot, write_version) <= (last_slot, last_wv) => false,
+            _ => {
+                self.seen.insert(pubkey.to_vec(), (slot, write_version));
+                true
+            }
+        }
+    }
+}
+
+/// An update forwarded from a source task to the merge loop in `main`.
+enum SourceEvent {
+    Account(SubscribeUpdateAccount),
+    Slot(SubscribeUpdateSlot),
+    /// A source is about to reconnect after a disconnect; the merge loop
+    /// flushes the sink so nothing buffered is lost if the retry takes a
+    /// while or never recovers.
+    Reconnecting(usize),
+}
+
+/// Connects to a single geyser endpoint and forwards `Account` and `Slot`
+/// updates onto `out`. Reconnects and resubscribes with exponential backoff
+/// whenever the stream ends or errors, so a single flaky endpoint never
+/// takes the whole tool down.
+async fn run_source(source: Source, request: SubscribeRequest, out: mpsc::UnboundedSender<SourceEvent>) {
+    let mut backoff = Duration::from_millis(500);
+    const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+    loop {
+        let connected = async {
+            let mut client = GeyserGrpcClient::build_from_shared(source.endpoint.clone())?
+                .x_token(Some(source.x_token.clone()))?
+                .tls_config(ClientTlsConfig::new().with_native_roots())?
+                .connect()
+                .await?;
+            let (mut tx, mut stream) = client.subscribe_with_request(Some(request.clone())).await?;
+            println!("[source {}] subscribed to {}", source.id, source.endpoint);
+
+            while let Some(message) = stream.next().await {
+                let message = message?;
+                match message.update_oneof {
+                    Some(UpdateOneof::Account(acc)) => {
+                        if out.send(SourceEvent::Account(acc)).is_err() {
+                            // Receiver dropped, nothing left to do.
+                            return Ok::<(), anyhow::Error>(());
+                        }
+                    }
+                    Some(UpdateOneof::Slot(slot)) => {
+                        if out.send(SourceEvent::Slot(slot)).is_err() {
+                            return Ok::<(), anyhow::Error>(());
+                        }
+                    }
+                    Some(UpdateOneof::Ping(_)) => {
+                        tx.send(SubscribeRequest {
+                            ping: Some(SubscribeRequestPing { id: 1 }),
+                            ..Default::default()
+                        })
+                        .await
+                        .ok();
+                    }
+                    _ => {}
+                }
+            }
+            Ok(())
+        }
+        .await;
+
+        if out.is_closed() {
+            return;
+        }
+        if let Err(e) = connected {
+            eprintln!("[source {}] {} disconnected: {e}, retrying in {backoff:?}", source.id, source.endpoint);
+        } else {
+            eprintln!("[source {}] {} stream ended, retrying in {backoff:?}", source.id, source.endpoint);
+        }
+        out.send(SourceEvent::Reconnecting(source.id)).ok();
+
+        tokio::time::sleep(backoff).await;
+        backoff = std::cmp::min(backoff * 2, MAX_BACKOFF);
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Parse CLI arguments
     let args = Args::parse();
+    if args.endpoints.len() != args.x_tokens.len() {
+        anyhow::bail!(
+            "expected one --x-token per --endpoint, got {} endpoint(s) and {} token(s)",
+            args.endpoints.len(),
+            args.x_tokens.len()
+        );
+    }
 
     // Prompt for account pubkeys
     let mut pool = String::new();
@@ -73,35 +355,72 @@ async fn main() -> anyhow::Result<()> {
     let base_mint = base_mint.trim().to_string();
     let quote_mint = quote_mint.trim().to_string();
 
+    // Mint accounts are effectively static, so don't wait on a geyser write
+    // to learn their decimals - fetch both once over RPC up front.
+    let rpc = RpcClient::new(args.rpc_url.clone());
+    let base_decimals = fetch_mint_decimals(&rpc, &base_mint).await?;
+    let quote_decimals = fetch_mint_decimals(&rpc, &quote_mint).await?;
+
     // Initialize in-memory database with static fields
     let mut ram_db = RamDb {
         pool_address: pool.clone(),
         base_vault_address: base.clone(),
         quote_vault_address: quote.clone(),
-        base_updates: Vec::new(),
-        quote_updates: Vec::new(),
+        slot_gaps: Vec::new(),
+        price_ticks: Vec::new(),
     };
 
-    // Connect to the Geyser gRPC endpoint
-    let mut client = GeyserGrpcClient::build_from_shared(args.endpoint)?
-        .x_token(Some(args.x_token))?
-        .tls_config(ClientTlsConfig::new().with_native_roots())?
-        .connect()
-        .await?;
+    // Vault updates are persisted through whichever sink was configured -
+    // in memory by default, or Postgres if `--postgres-url` was given.
+    let mut sink: Box<dyn Sink> = match &args.postgres_url {
+        Some(url) => Box::new(PostgresSink::connect(url).await?),
+        None => Box::new(MemorySink::default()),
+    };
 
-    // Build subscription filter for the desired accounts
+    // Build subscription filter for the desired accounts, plus any
+    // server-side owner/memcmp/datasize filters so the source can match
+    // accounts (e.g. SPL token accounts for a vault authority) that were
+    // never typed at the prompt. The mints aren't subscribed to - their
+    // decimals were already fetched over RPC above.
     let accounts = vec![pool.clone(), base.clone(), quote.clone()];
 
-    let filter = SubscribeRequestFilterAccounts {
-        account: accounts,
-        owner: Vec::new(),
-        nonempty_txn_signature: None,
-        filters: Vec::new(),
+    let mut account_filters = Vec::new();
+    for (offset, base58_bytes) in args.memcmps {
+        account_filters.push(SubscribeRequestFilterAccountsFilter {
+            filter: Some(AccountsFilterOneof::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+                offset,
+                data: Some(MemcmpDataOneof::Base58(base58_bytes)),
+            })),
+        });
+    }
+    for datasize in args.datasizes {
+        account_filters.push(SubscribeRequestFilterAccountsFilter {
+            filter: Some(AccountsFilterOneof::Datasize(datasize)),
+        });
+    }
+
+    // Geyser ANDs the fields of a single accounts filter, so the typed
+    // pubkeys and the owner/memcmp/datasize filters go into separate named
+    // filters; an account matching either one is delivered.
+    let watched = SubscribeRequestFilterAccounts { account: accounts, ..Default::default() };
+    let mut accounts_filters = HashMap::from([("watched".to_string(), watched)]);
+    if !args.owners.is_empty() || !account_filters.is_empty() {
+        let discovered =
+            SubscribeRequestFilterAccounts { owner: args.owners, filters: account_filters, ..Default::default() };
+        accounts_filters.insert("discovered".to_string(), discovered);
+    }
+
+    // Don't restrict by commitment: we want every status (processed,
+    // confirmed, finalized) for each slot, both to detect gaps in the
+    // processed sequence and to later mark vault updates as confirmed.
+    let slots_filter = SubscribeRequestFilterSlots {
+        filter_by_commitment: Some(false),
+        interslot_updates: Some(false),
     };
 
     let request = SubscribeRequest {
-        accounts: std::iter::once(("watched".into(), filter)).collect(),
-        slots: Default::default(),
+        accounts: accounts_filters,
+        slots: std::iter::once(("slots".into(), slots_filter)).collect(),
         transactions: Default::default(),
         transactions_status: Default::default(),
         blocks: Default::default(),
@@ -113,84 +432,191 @@ async fn main() -> anyhow::Result<()> {
         from_slot: None,
     };
 
-    // Subscribe once and start the stream
-    let (mut tx, mut stream) = client.subscribe_with_request(Some(request)).await?;
-    println!("Subscribed - receiving updates. Ctrl+C to exit.");
+    // Spawn one reconnecting task per endpoint; they all feed into `rx`.
+    let source_count = args.endpoints.len();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    for (id, (endpoint, x_token)) in args.endpoints.into_iter().zip(args.x_tokens.into_iter()).enumerate() {
+        let source = Source { id, endpoint, x_token };
+        tokio::spawn(run_source(source, request.clone(), tx.clone()));
+    }
+    drop(tx);
+    println!("Racing {source_count} source(s) - receiving updates. Ctrl+C to exit.");
 
-    // Determine token decimals based on mint addresses
-    let base_decimals = if base_mint == "So11111111111111111111111111111111111111112" {
-        9
-    } else {
-        6
-    };
-    let quote_decimals = if quote_mint == "So11111111111111111111111111111111111111112" {
-        9
-    } else {
-        6
-    };
+    let mut dedup = Dedup::default();
+    let mut slot_gaps = SlotGapTracker::default();
 
-    // Handle incoming updates
-    while let Some(message) = stream.next().await {
-        let message = message?;
-        match message.update_oneof {
-            Some(UpdateOneof::Account(acc)) => {
-                if let Some(data) = acc.account {
-                    let pk = bs58::encode(&data.pubkey).into_string();
-                    // Pool PDA -> print lp_supply
-                    if pk == pool {
-                        let pool_state = PoolAccount::deserialize(&data.data)
-                            .map_err(|e| anyhow::anyhow!("Failed to deserialize PoolAccount: {}", e))?;
-                        let info = pool_state.0;
-                        println!(
-                            "Pool PDA {} @ slot {}: base_mint={} quote_mint={} lp_supply={}",
-                            pk, acc.slot, info.base_mint, info.quote_mint, info.lp_supply
-                        );
-                    }
-                    // Base vault -> token balance
-                    else if pk == base {
-                        match SplAccount::unpack(&data.data) {
-                            Ok(token_acc) => {
-                                let raw = token_acc.amount;
-                                let human = (raw as f64) / 10f64.powi(base_decimals as i32);
-                                println!(
-                                    "Base vault {} @ slot {}: {:.6} tokens (raw={})",
-                                    pk, acc.slot, human, raw
-                                );
-                                ram_db.base_updates.push(VaultUpdate { slot: acc.slot, amount: human });
-                            }
-                            Err(e) => eprintln!("Failed to unpack base vault {}: {}", pk, e),
-                        }
-                    }
-                    // Quote vault -> SOL balance
-                    else if pk == quote {
-                        match SplAccount::unpack(&data.data) {
-                            Ok(token_acc) => {
-                                let raw = token_acc.amount;
-                                let human = (raw as f64) / 10f64.powi(quote_decimals as i32);
-                                println!(
-                                    "Quote vault {} @ slot {}: {:.6} tokens (raw={})",
-                                    pk, acc.slot, human, raw
-                                );
-                                ram_db.quote_updates.push(VaultUpdate { slot: acc.slot, amount: human });
-                            }
-                            Err(e) => eprintln!("Failed to unpack quote vault {}: {}", pk, e),
-                        }
+    // Most recent `(slot, amount)` seen for each vault, used to derive
+    // live price ticks whenever either side updates.
+    let mut latest_base: Option<(u64, f64)> = None;
+    let mut latest_quote: Option<(u64, f64)> = None;
+    let mut latest_lp_supply: Option<u64> = None;
+
+    let mut ctrl_c = Box::pin(tokio::signal::ctrl_c());
+
+    // Handle incoming updates, merged and deduplicated across all sources
+    'merge: loop {
+        let event = tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => event,
+                None => break 'merge,
+            },
+            _ = &mut ctrl_c => {
+                println!("Ctrl+C received, flushing sink...");
+                sink.flush().await?;
+                break 'merge;
+            }
+        };
+
+        let acc = match event {
+            SourceEvent::Reconnecting(id) => {
+                eprintln!("[source {id}] flushing sink before reconnect");
+                sink.flush().await?;
+                continue;
+            }
+            SourceEvent::Slot(slot_update) => {
+                if slot_update.status == SlotStatus::SlotProcessed as i32 {
+                    if let Some((start, end)) = slot_gaps.observe(slot_update.slot) {
+                        eprintln!("Slot gap detected: missing slots {start}..={end}");
+                        ram_db.slot_gaps.push((start, end));
                     }
+                } else if slot_update.status == SlotStatus::SlotConfirmed as i32
+                    || slot_update.status == SlotStatus::SlotFinalized as i32
+                {
+                    sink.mark_confirmed(slot_update.slot).await?;
                 }
+                continue;
             }
-            Some(UpdateOneof::Ping(_)) => {
-                // Respond to heartbeat
-                tx.send(SubscribeRequest {
-                    ping: Some(SubscribeRequestPing { id: 1 }),
-                    ..Default::default()
-                })
-                .await
-                .ok();
+            SourceEvent::Account(acc) => acc,
+        };
+
+        if let Some(data) = acc.account {
+            if !dedup.admit(&data.pubkey, acc.slot, data.write_version) {
+                // A different source already delivered this update (or a
+                // newer one), so skip it to avoid overwriting fresher state.
+                continue;
+            }
+
+            let pk = bs58::encode(&data.pubkey).into_string();
+            // Pool PDA -> print lp_supply
+            if pk == pool {
+                let pool_state = PoolAccount::deserialize(&data.data)
+                    .map_err(|e| anyhow::anyhow!("Failed to deserialize PoolAccount: {}", e))?;
+                let info = pool_state.0;
+                println!(
+                    "Pool PDA {} @ slot {}: base_mint={} quote_mint={} lp_supply={}",
+                    pk, acc.slot, info.base_mint, info.quote_mint, info.lp_supply
+                );
+                latest_lp_supply = Some(info.lp_supply);
+            }
+            // Base vault -> token balance
+            else if pk == base {
+                process_vault_update(
+                    "Base", &pk, acc.slot, &data.data, base_decimals, VaultSide::Base, &base,
+                    sink.as_mut(), &mut latest_base, latest_quote, latest_lp_supply, &mut ram_db,
+                )
+                .await?;
+            }
+            // Quote vault -> SOL balance
+            else if pk == quote {
+                process_vault_update(
+                    "Quote", &pk, acc.slot, &data.data, quote_decimals, VaultSide::Quote, &quote,
+                    sink.as_mut(), &mut latest_quote, latest_base, latest_lp_supply, &mut ram_db,
+                )
+                .await?;
+            }
+            // Not one of the pubkeys typed at the prompt, so it can only
+            // have matched via --owner/--memcmp/--datasize. Classify it by
+            // mint so dynamically discovered vaults get recorded the same
+            // way as the ones we already knew about.
+            else if let Ok(token_acc) = SplAccount::unpack(&data.data) {
+                let mint = bs58::encode(token_acc.mint).into_string();
+                if mint == base_mint {
+                    process_vault_update(
+                        "Base", &pk, acc.slot, &data.data, base_decimals, VaultSide::Base, &pk,
+                        sink.as_mut(), &mut latest_base, latest_quote, latest_lp_supply, &mut ram_db,
+                    )
+                    .await?;
+                } else if mint == quote_mint {
+                    process_vault_update(
+                        "Quote", &pk, acc.slot, &data.data, quote_decimals, VaultSide::Quote, &pk,
+                        sink.as_mut(), &mut latest_quote, latest_base, latest_lp_supply, &mut ram_db,
+                    )
+                    .await?;
+                } else {
+                    eprintln!("Discovered token account {pk} @ slot {}: mint {mint} isn't base or quote, ignoring", acc.slot);
+                }
             }
-            _ => {}
         }
     }
 
+    sink.flush().await?;
     println!("Final RAM DB: {:?}", ram_db);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup_admits_strictly_newer_updates_only() {
+        let mut dedup = Dedup::default();
+        let pk = b"pubkey".to_vec();
+        assert!(dedup.admit(&pk, 10, 1));
+        assert!(!dedup.admit(&pk, 10, 1)); // exact repeat
+        assert!(!dedup.admit(&pk, 10, 0)); // older write_version
+        assert!(!dedup.admit(&pk, 9, 5)); // older slot
+        assert!(dedup.admit(&pk, 10, 2)); // newer write_version
+        assert!(dedup.admit(&pk, 11, 0)); // newer slot
+    }
+
+    #[test]
+    fn dedup_tracks_each_pubkey_independently() {
+        let mut dedup = Dedup::default();
+        assert!(dedup.admit(b"a", 5, 0));
+        assert!(dedup.admit(b"b", 1, 0));
+    }
+
+    #[test]
+    fn slot_gap_tracker_reports_missing_range() {
+        let mut tracker = SlotGapTracker::default();
+        assert_eq!(tracker.observe(100), None);
+        assert_eq!(tracker.observe(101), None);
+        assert_eq!(tracker.observe(105), Some((102, 104)));
+        assert_eq!(tracker.observe(106), None);
+    }
+
+    #[test]
+    fn slot_gap_tracker_ignores_out_of_order_slots() {
+        let mut tracker = SlotGapTracker::default();
+        tracker.observe(100);
+        assert_eq!(tracker.observe(50), None);
+        assert_eq!(tracker.observe(101), None);
+    }
+
+    #[test]
+    fn price_tick_requires_both_sides_and_adjacent_slots() {
+        assert!(try_derive_price_tick(Some((10, 1.0)), None, None).is_none());
+        assert!(try_derive_price_tick(None, Some((10, 1.0)), None).is_none());
+        assert!(try_derive_price_tick(Some((10, 1.0)), Some((12, 1.0)), None).is_none());
+    }
+
+    #[test]
+    fn price_tick_skips_zero_base_amount() {
+        assert!(try_derive_price_tick(Some((10, 0.0)), Some((10, 5.0)), None).is_none());
+    }
+
+    #[test]
+    fn price_tick_computes_price_and_liquidity() {
+        let tick = try_derive_price_tick(Some((10, 2.0)), Some((11, 10.0)), None).unwrap();
+        assert_eq!(tick.slot, 11);
+        assert_eq!(tick.price, 5.0);
+        assert_eq!(tick.liquidity_quote, 20.0);
+    }
+
+    #[test]
+    fn price_tick_carries_lp_supply_through_for_display() {
+        let tick = try_derive_price_tick(Some((10, 2.0)), Some((10, 10.0)), Some(12_345)).unwrap();
+        assert_eq!(tick.lp_supply, Some(12_345));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::VaultUpdate;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+
+/// Which vault an update came from, for sinks that persist both streams
+/// into a single table/collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultSide {
+    Base,
+    Quote,
+}
+
+/// A destination for vault updates. `record_update` may buffer as it sees
+/// fit; `flush` must make everything buffered so far durable before it
+/// returns, so it's safe to call on reconnect or shutdown without losing
+/// slot data.
+#[async_trait]
+pub trait Sink: Send {
+    async fn record_update(&mut self, update: &VaultUpdate, side: VaultSide, pubkey: &str) -> Result<()>;
+    async fn flush(&mut self) -> Result<()>;
+
+    /// Best-effort: mark a previously recorded slot as confirmed/finalized.
+    /// Sinks for which this would be expensive to rewrite may ignore it.
+    async fn mark_confirmed(&mut self, _slot: u64) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps every update in memory - the tool's original behavior, now
+/// expressed as a `Sink` so it can be swapped for a durable backend.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    pub updates: Vec<(VaultSide, String, VaultUpdate)>,
+    /// Row indices into `updates`, keyed by slot, so confirming a slot
+    /// only touches that slot's rows instead of rescanning all history.
+    by_slot: HashMap<u64, Vec<usize>>,
+}
+
+#[async_trait]
+impl Sink for MemorySink {
+    async fn record_update(&mut self, update: &VaultUpdate, side: VaultSide, pubkey: &str) -> Result<()> {
+        self.by_slot.entry(update.slot).or_default().push(self.updates.len());
+        self.updates.push((side, pubkey.to_string(), update.clone()));
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn mark_confirmed(&mut self, slot: u64) -> Result<()> {
+        if let Some(indices) = self.by_slot.get(&slot) {
+            for &idx in indices {
+                self.updates[idx].2.confirmed = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Batches updates and flushes them to Postgres with a bulk `COPY` rather
+/// than one `INSERT` per row, so a long-running session doesn't bottleneck
+/// on round trips.
+pub struct PostgresSink {
+    client: tokio_postgres::Client,
+    buffered: Vec<(VaultSide, String, VaultUpdate)>,
+    batch_size: usize,
+    /// Slots already marked confirmed, so the `Confirmed` and `Finalized`
+    /// status transitions for the same slot don't each pay for an `UPDATE`.
+    confirmed_slots: HashSet<u64>,
+}
+
+impl PostgresSink {
+    pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+    pub async fn connect(url: &str) -> Result<Self> {
+        let (client, connection) = tokio_postgres::connect(url, tokio_postgres::NoTls)
+            .await
+            .context("connecting to postgres")?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("postgres connection error: {e}");
+            }
+        });
+        client
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS vault_updates (
+                    side      TEXT NOT NULL,
+                    pubkey    TEXT NOT NULL,
+                    slot      BIGINT NOT NULL,
+                    amount    DOUBLE PRECISION NOT NULL,
+                    confirmed BOOLEAN NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS vault_updates_slot_idx ON vault_updates (slot)",
+            )
+            .await
+            .context("creating vault_updates table")?;
+        Ok(Self {
+            client,
+            buffered: Vec::new(),
+            batch_size: Self::DEFAULT_BATCH_SIZE,
+            confirmed_slots: HashSet::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for PostgresSink {
+    async fn record_update(&mut self, update: &VaultUpdate, side: VaultSide, pubkey: &str) -> Result<()> {
+        self.buffered.push((side, pubkey.to_string(), update.clone()));
+        if self.buffered.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffered.is_empty() {
+            return Ok(());
+        }
+
+        use futures::SinkExt as _;
+        use std::io::Write as _;
+
+        let sink = self
+            .client
+            .copy_in("COPY vault_updates (side, pubkey, slot, amount, confirmed) FROM STDIN WITH (FORMAT csv)")
+            .await
+            .context("starting COPY IN")?;
+        futures::pin_mut!(sink);
+
+        let mut csv = Vec::new();
+        for (side, pubkey, update) in self.buffered.drain(..) {
+            let side = match side {
+                VaultSide::Base => "base",
+                VaultSide::Quote => "quote",
+            };
+            writeln!(csv, "{side},{pubkey},{},{},{}", update.slot, update.amount, update.confirmed)
+                .expect("writing to an in-memory buffer cannot fail");
+        }
+        sink.send(bytes::Bytes::from(csv)).await.context("writing COPY rows")?;
+        sink.close().await.context("finishing COPY IN")?;
+        Ok(())
+    }
+
+    async fn mark_confirmed(&mut self, slot: u64) -> Result<()> {
+        if !self.confirmed_slots.insert(slot) {
+            // Already confirmed - the Confirmed and Finalized status
+            // transitions both land here, so skip the repeat work.
+            return Ok(());
+        }
+
+        // Updates for this slot still sitting in `buffered` haven't been
+        // COPYed yet; flip them directly so they don't land durably as
+        // `confirmed=false` once `flush` finally writes them out.
+        for (_, _, update) in self.buffered.iter_mut() {
+            if update.slot == slot {
+                update.confirmed = true;
+            }
+        }
+
+        // Rows for this slot may already be durably COPYed, so rewriting
+        // them in bulk isn't worth a round trip per confirmation - a
+        // plain targeted UPDATE (backed by `vault_updates_slot_idx`) is
+        // cheaper here than folding this into COPY.
+        self.client
+            .execute("UPDATE vault_updates SET confirmed = true WHERE slot = $1", &[&(slot as i64)])
+            .await
+            .context("marking slot confirmed")?;
+        Ok(())
+    }
+}